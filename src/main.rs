@@ -1,7 +1,10 @@
 pub(crate) use anyhow::{bail, Context, Error, Result};
 use getopt::Opt;
 use notify_rust::{Notification, Timeout, Urgency};
+use std::collections::HashMap;
 use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
 use std::path::Path;
 use std::process::{exit, Command};
 use std::str::FromStr;
@@ -47,12 +50,83 @@ impl FromStr for BatteryStatus {
     }
 }
 
+/// A power management action to perform when the battery reaches the danger
+/// level, on top of running `dangercmd`.
+#[derive(Debug, PartialEq, Clone)]
+enum PowerAction {
+    None,
+    Suspend,
+    Hibernate,
+    Shutdown,
+}
+
+impl FromStr for PowerAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "none" => Ok(PowerAction::None),
+            "suspend" => Ok(PowerAction::Suspend),
+            "hibernate" => Ok(PowerAction::Hibernate),
+            "shutdown" => Ok(PowerAction::Shutdown),
+            other => Err(Error::msg(format!(
+                "Failed to parse power action, found {other}"
+            ))),
+        }
+    }
+}
+
+/// Which sysfs attributes a battery's charge level was last read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChargeSource {
+    /// `energy_now` / `energy_full` (µWh)
+    Energy,
+    /// `charge_now` / `charge_full` (µAh)
+    Charge,
+    /// `capacity` (already a percentage)
+    Capacity,
+}
+
+/// A source of battery readings, so the main loop doesn't need to care
+/// whether it's talking to a sysfs battery or a UPS.
+trait BatteryDevice: std::fmt::Debug {
+    /// Refreshes this device's readings from its backing source.
+    fn refresh(&mut self) -> Result<()>;
+    /// Current charge level as a percentage, or `None` if unavailable this cycle.
+    fn percent(&self) -> Option<i32>;
+    /// Whether the device is currently running off battery power.
+    fn discharging(&self) -> bool;
+    /// Estimated seconds remaining until empty (discharging) or full (charging).
+    fn remaining_seconds(&self) -> Option<i64>;
+    /// Raw (energy, power) components behind `remaining_seconds`, in units
+    /// where `3600 * energy / power` is seconds. Used to aggregate several
+    /// devices by summing energy and power *before* dividing once, instead of
+    /// averaging each device's already-computed time. `None` for devices
+    /// (such as a UPS) that only expose a precomputed estimate.
+    fn remaining_components(&self) -> Option<(i64, i64)> {
+        None
+    }
+    /// Name shown in notifications and startup messages.
+    fn name(&self) -> &str;
+}
+
 #[derive(Debug)]
 struct Battery {
     name: String,
     status: BatteryStatus,
     energy_full: i32,
     energy_now: i32,
+    capacity: Option<i32>,
+    /// `None` when the last update couldn't find any usable charge attribute
+    /// for this battery, so it should be skipped rather than aborting.
+    source: Option<ChargeSource>,
+    /// Instantaneous power draw/input in µW, used to estimate time remaining
+    /// for `ChargeSource::Energy` batteries.
+    power_now: Option<i64>,
+    /// Instantaneous current draw/input in µA, used to estimate time
+    /// remaining for `ChargeSource::Charge` batteries (whose energy is in
+    /// µAh, not µWh, so power_now can't be used without a unit mismatch).
+    current_now: Option<i64>,
 }
 
 impl Battery {
@@ -64,11 +138,199 @@ impl Battery {
                 status: BatteryStatus::Discharging,
                 energy_full: 0,
                 energy_now: 0,
+                capacity: None,
+                source: None,
+                power_now: None,
+                current_now: None,
             })
         } else {
             bail!("Battery {name} not found")
         }
     }
+
+    /// Charge level as a percentage, using whichever attribute `source` says
+    /// was last read successfully.
+    fn percent(&self) -> Option<i32> {
+        match self.source? {
+            ChargeSource::Energy | ChargeSource::Charge => {
+                if self.energy_full == 0 {
+                    None
+                } else {
+                    Some((self.energy_now as f64 / self.energy_full as f64 * 100.0) as i32)
+                }
+            }
+            ChargeSource::Capacity => self.capacity,
+        }
+    }
+}
+
+impl BatteryDevice for Battery {
+    fn refresh(&mut self) -> Result<()> {
+        let path = Path::new(POWER_SUPLY_DIR).join(self.name.as_str());
+
+        update_charge(self, &path)?;
+        if self.source.is_none() {
+            eprintln!(
+                "Warning: no energy_*, charge_*, or capacity attribute found for {}, skipping",
+                self.name
+            );
+            return Ok(());
+        }
+
+        self.status = fs::read_to_string(path.join("status"))?
+            .trim()
+            .parse()
+            .with_context(|| format!("Error parsing status for {}", self.name))?;
+
+        update_power(self, &path);
+
+        Ok(())
+    }
+
+    fn percent(&self) -> Option<i32> {
+        Battery::percent(self)
+    }
+
+    fn discharging(&self) -> bool {
+        self.source.is_some() && self.status == BatteryStatus::Discharging
+    }
+
+    fn remaining_seconds(&self) -> Option<i64> {
+        let (energy, power) = self.remaining_components()?;
+        if power == 0 {
+            return None;
+        }
+
+        Some((3600 * energy / power).max(0))
+    }
+
+    fn remaining_components(&self) -> Option<(i64, i64)> {
+        let energy = if self.discharging() {
+            self.energy_now as i64
+        } else {
+            (self.energy_full - self.energy_now) as i64
+        };
+
+        match self.source? {
+            // `capacity` is already a percentage, not an absolute energy
+            // value, so there's nothing here to divide by a power/current.
+            ChargeSource::Capacity => None,
+            ChargeSource::Energy => Some((energy, self.power_now?)),
+            // charge_now/charge_full are in µAh, so they must be divided by
+            // current_now (µA), not power_now (µW), or the result is off by
+            // roughly the pack voltage.
+            ChargeSource::Charge => Some((energy, self.current_now?)),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A UPS monitored over apcupsd's NIS protocol, as an alternative to sysfs
+/// batteries for desktops that don't have one. This is apcupsd's own binary
+/// length-prefixed framing, not NUT's line-based `upsd` protocol, so it only
+/// talks to `apcupsd` itself, not to a stock NUT server.
+#[derive(Debug)]
+struct UpsBattery {
+    address: String,
+    charge_percent: Option<i32>,
+    discharging: bool,
+    remaining_seconds: Option<i64>,
+}
+
+impl UpsBattery {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            charge_percent: None,
+            discharging: false,
+            remaining_seconds: None,
+        }
+    }
+
+    /// Connects to `apcupsd` and issues a `status` query, returning the
+    /// response as a map of field name to value.
+    fn query_status(&self) -> Result<HashMap<String, String>> {
+        let mut stream = TcpStream::connect(&self.address)
+            .with_context(|| format!("Failed to connect to UPS at {}", self.address))?;
+
+        let command = b"status";
+        let mut request = Vec::with_capacity(command.len() + 2);
+        request.extend_from_slice(&(command.len() as u16).to_be_bytes());
+        request.extend_from_slice(command);
+        stream
+            .write_all(&request)
+            .with_context(|| format!("Failed to query UPS at {}", self.address))?;
+
+        let mut fields = HashMap::new();
+        loop {
+            let mut len_buf = [0u8; 2];
+            if stream.read_exact(&mut len_buf).is_err() {
+                break;
+            }
+            let len = u16::from_be_bytes(len_buf) as usize;
+            if len == 0 {
+                break;
+            }
+
+            let mut line_buf = vec![0u8; len];
+            stream
+                .read_exact(&mut line_buf)
+                .with_context(|| format!("Failed to read UPS response from {}", self.address))?;
+
+            if let Some((key, value)) = String::from_utf8_lossy(&line_buf).split_once(':') {
+                fields.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        Ok(fields)
+    }
+}
+
+impl BatteryDevice for UpsBattery {
+    fn refresh(&mut self) -> Result<()> {
+        let fields = self.query_status()?;
+
+        self.charge_percent = fields
+            .get("BCHARGE")
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|v| v as i32);
+
+        self.remaining_seconds = fields
+            .get("TIMELEFT")
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|m| (m * 60.0) as i64);
+
+        let status = fields
+            .get("STATUS")
+            .map(String::as_str)
+            .unwrap_or_default();
+        self.discharging = status.contains("ONBATT") || status.contains("OB");
+
+        Ok(())
+    }
+
+    fn percent(&self) -> Option<i32> {
+        self.charge_percent
+    }
+
+    fn discharging(&self) -> bool {
+        self.discharging
+    }
+
+    fn remaining_seconds(&self) -> Option<i64> {
+        // TIMELEFT is always runtime to empty, so there's no time to full to
+        // report while the UPS isn't on battery.
+        self.remaining_seconds.filter(|_| self.discharging)
+    }
+
+    fn name(&self) -> &str {
+        &self.address
+    }
 }
 
 #[derive(Debug)]
@@ -76,7 +338,10 @@ struct Settings {
     daemonize: bool,
     run_once: bool,
 
-    batteries: Vec<Battery>,
+    devices: Vec<Box<dyn BatteryDevice>>,
+    /// `HOST:PORT` of a UPS to monitor over apcupsd's NIS protocol instead
+    /// of sysfs batteries, set via `-u`.
+    ups: Option<String>,
 
     sleep_interval: i32,
 
@@ -89,9 +354,16 @@ struct Settings {
     criticalmsg: String,
     fullmsg: String,
 
+    bodymsg: String,
+
     dangercmd: Option<String>,
+    poweraction: PowerAction,
+    poweraction_fallback: Option<String>,
     appname: String,
     icon: Option<String>,
+    /// Icons for each 10% charge bucket (0, 10, .., 100), plus an optional
+    /// 12th entry used while charging. Takes priority over `icon`.
+    icon_levels: Option<Vec<String>>,
     notification_timeout: Timeout,
 }
 
@@ -101,7 +373,8 @@ impl Default for Settings {
             daemonize: true,
             run_once: false,
 
-            batteries: Vec::new(),
+            devices: Vec::new(),
+            ups: None,
 
             sleep_interval: 60,
 
@@ -114,9 +387,14 @@ impl Default for Settings {
             criticalmsg: "Battery is critically low".to_string(),
             fullmsg: "Battery is full".to_string(),
 
+            bodymsg: "Battery level: {percent}% (~{time} remaining)".to_string(),
+
             dangercmd: None,
+            poweraction: PowerAction::None,
+            poweraction_fallback: None,
             appname: PROGNAME.to_string(),
             icon: None,
+            icon_levels: None,
             notification_timeout: Timeout::Never,
         }
     }
@@ -208,14 +486,29 @@ fn print_help() {
     -W MESSAGE     show MESSAGE when battery is at warning level
     -C MESSAGE     show MESSAGE when battery is at critical level
     -D COMMAND     run COMMAND when battery is at danger level
+    -p ACTION      perform ACTION (suspend, hibernate, shutdown, none) when
+                   battery is at danger level, after running -D's COMMAND
+                   (default: none)
+    -P COMMAND     fallback COMMAND to run ACTION on non-systemd systems
     -F MESSAGE     show MESSAGE when battery is full
+    -B MESSAGE     body MESSAGE used in every notification
+                   (default: Battery level: {{percent}}% (~{{time}} remaining))
     -n NAME        use battery NAME - multiple batteries separated by commas
                    (default: BAT0)
     -s SECONDS     number of SECONDS to wait between battery checks
                    (default: 60)
     -a NAME        app NAME used in desktop notifications
                    (default: {PROGNAME})
-    -I ICON        display specified ICON in notifications\n\
+    -I ICON        display specified ICON in notifications
+    -L ICONS       comma-separated list of 11 ICONS for the 0/10/../100%
+                   charge buckets, plus an optional 12th charging ICON;
+                   overrides -I and picks an icon by charge level
+    -u HOST:PORT   monitor a UPS over apcupsd's NIS protocol at HOST:PORT
+                   instead of sysfs batteries (overrides -n)
+    \n\
+    Messages (-W, -C, -F, -B) may use {{percent}}, {{state}}, {{time}},
+    {{battery}} and {{plural}} placeholders, substituted when shown.
+    Use {{{{ and }}}} for literal braces.\n\
     "
     )
 }
@@ -225,11 +518,11 @@ fn print_version() {
 }
 
 fn handle_battery_names(settings: &mut Settings, battery_names: &str) -> Result<()> {
-    settings.batteries = battery_names
+    settings.devices = battery_names
         .replace(' ', "")
         .split(',')
-        .map(Battery::new)
-        .collect::<Result<Vec<Battery>>>()?;
+        .map(|name| Battery::new(name).map(|battery| Box::new(battery) as Box<dyn BatteryDevice>))
+        .collect::<Result<Vec<Box<dyn BatteryDevice>>>>()?;
 
     Ok(())
 }
@@ -238,7 +531,7 @@ fn parse_args() -> Result<Settings> {
     let mut settings = Settings::default();
 
     let args: Vec<String> = std::env::args().collect();
-    let mut opts = getopt::Parser::new(&args, "hvboew:c:d:f:W:C:D:F:n:s:a:I:");
+    let mut opts = getopt::Parser::new(&args, "hvboew:c:d:f:W:C:D:p:P:F:B:n:s:a:I:L:u:");
 
     loop {
         match opts
@@ -288,7 +581,16 @@ fn parse_args() -> Result<Settings> {
                 Opt('W', Some(warningmsg)) => settings.warningmsg = warningmsg,
                 Opt('C', Some(criticalmsg)) => settings.criticalmsg = criticalmsg,
                 Opt('D', dangercmd) => settings.dangercmd = dangercmd,
+                Opt('p', Some(poweraction)) => {
+                    settings.poweraction = poweraction
+                        .parse()
+                        .with_context(|| "Error parsing argument for option p")?
+                }
+                Opt('P', poweraction_fallback) => {
+                    settings.poweraction_fallback = poweraction_fallback
+                }
                 Opt('F', Some(fullmsg)) => settings.fullmsg = fullmsg,
+                Opt('B', Some(bodymsg)) => settings.bodymsg = bodymsg,
                 Opt('n', Some(battery_names)) => {
                     handle_battery_names(&mut settings, battery_names.as_str())?
                 }
@@ -299,7 +601,12 @@ fn parse_args() -> Result<Settings> {
                 }
                 Opt('a', Some(appname)) => settings.appname = appname,
                 Opt('I', icon) => settings.icon = icon,
+                Opt('L', Some(icon_levels)) => {
+                    settings.icon_levels =
+                        Some(icon_levels.split(',').map(|s| s.to_string()).collect())
+                }
                 Opt('e', None) => settings.notification_timeout = Timeout::Default,
+                Opt('u', Some(ups)) => settings.ups = Some(ups),
                 _ => unreachable!(),
             },
         }
@@ -308,8 +615,8 @@ fn parse_args() -> Result<Settings> {
     Ok(settings)
 }
 
-fn find_batteries() -> Result<Vec<Battery>> {
-    let mut found_batteries: Vec<Battery> = Vec::new();
+fn find_batteries() -> Result<Vec<Box<dyn BatteryDevice>>> {
+    let mut found_batteries: Vec<Box<dyn BatteryDevice>> = Vec::new();
 
     for f in fs::read_dir(POWER_SUPLY_DIR)? {
         let f_path = f?.path();
@@ -318,7 +625,7 @@ fn find_batteries() -> Result<Vec<Battery>> {
             && f_path.join("type").exists()
             && fs::read_to_string(f_path.join("type"))?.contains("Battery")
         {
-            found_batteries.push(Battery::new(
+            found_batteries.push(Box::new(Battery::new(
                 f_path
                     .file_name()
                     .ok_or_else(|| anyhow::Error::msg("Invalid file name"))?
@@ -326,7 +633,7 @@ fn find_batteries() -> Result<Vec<Battery>> {
                     .ok_or_else(|| {
                         anyhow::Error::msg("Failed to convert battery name to string")
                     })?,
-            )?);
+            )?));
         }
     }
 
@@ -337,69 +644,301 @@ fn find_batteries() -> Result<Vec<Battery>> {
     }
 }
 
-fn update_batteries(batteries: &mut Vec<Battery>) -> Result<()> {
-    for battery in batteries {
-        let path = Path::new(POWER_SUPLY_DIR).join(battery.name.as_str());
+/// Reads a battery's charge level, preferring `energy_now`/`energy_full`,
+/// falling back to `charge_now`/`charge_full`, and finally `capacity`. Leaves
+/// `battery.source` as `None` if none of those attributes are present, so the
+/// battery is skipped instead of aborting the whole daemon.
+fn update_charge(battery: &mut Battery, path: &Path) -> Result<()> {
+    if path.join("energy_now").exists() && path.join("energy_full").exists() {
+        battery.source = Some(ChargeSource::Energy);
         battery.energy_now = fs::read_to_string(path.join("energy_now"))?
             .trim()
             .parse()
             .with_context(|| format!("Error parsing energy_now for {}", battery.name))?;
-
         battery.energy_full = fs::read_to_string(path.join("energy_full"))?
             .trim()
             .parse()
             .with_context(|| format!("Error parsing energy_full for {}", battery.name))?;
-
-        battery.status = fs::read_to_string(path.join("status"))?
+    } else if path.join("charge_now").exists() && path.join("charge_full").exists() {
+        battery.source = Some(ChargeSource::Charge);
+        battery.energy_now = fs::read_to_string(path.join("charge_now"))?
             .trim()
             .parse()
-            .with_context(|| format!("Error parsing status for {}", battery.name))?;
+            .with_context(|| format!("Error parsing charge_now for {}", battery.name))?;
+        battery.energy_full = fs::read_to_string(path.join("charge_full"))?
+            .trim()
+            .parse()
+            .with_context(|| format!("Error parsing charge_full for {}", battery.name))?;
+    } else if path.join("capacity").exists() {
+        battery.source = Some(ChargeSource::Capacity);
+        battery.capacity = Some(
+            fs::read_to_string(path.join("capacity"))?
+                .trim()
+                .parse()
+                .with_context(|| format!("Error parsing capacity for {}", battery.name))?,
+        );
+    } else {
+        battery.source = None;
+    }
+
+    Ok(())
+}
+
+/// Reads a battery's instantaneous power draw/current, preferring
+/// `power_now` (falling back to `current_now` (µA) × `voltage_now` (µV) when
+/// absent) for `power_now`, and always recording raw `current_now` (µA)
+/// separately for `ChargeSource::Charge` batteries. Missing attributes just
+/// leave the corresponding field as `None`.
+fn update_power(battery: &mut Battery, path: &Path) {
+    battery.current_now = fs::read_to_string(path.join("current_now"))
+        .ok()
+        .and_then(|s| s.trim().parse::<i64>().ok());
+
+    battery.power_now = fs::read_to_string(path.join("power_now"))
+        .ok()
+        .and_then(|s| s.trim().parse::<i64>().ok())
+        .or_else(|| {
+            let current = battery.current_now?;
+            let voltage: i64 = fs::read_to_string(path.join("voltage_now"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some(current * voltage / 1_000_000)
+        });
+}
+
+/// Estimates remaining seconds across all devices. Devices that expose raw
+/// energy/power components (e.g. sysfs batteries) are aggregated by summing
+/// those components and dividing once, so multiple packs of different sizes
+/// combine into a single estimate rather than an average of
+/// independently-computed times. A discharging pack contributes its energy
+/// to empty and any other pack its energy to full, all in the same sum.
+/// Devices that only expose a precomputed estimate (e.g. a UPS) are averaged
+/// in alongside that combined estimate. Returns `None` if nothing produced a
+/// usable figure this cycle.
+fn estimate_remaining_seconds(devices: &[Box<dyn BatteryDevice>]) -> Option<i64> {
+    let mut energy_sum = 0i64;
+    let mut power_sum = 0i64;
+    let mut estimates = Vec::new();
+
+    for device in devices {
+        if let Some((energy, power)) = device.remaining_components() {
+            energy_sum += energy;
+            power_sum += power;
+        } else if let Some(seconds) = device.remaining_seconds() {
+            estimates.push(seconds);
+        }
+    }
+
+    if power_sum != 0 {
+        estimates.push((3600 * energy_sum / power_sum).max(0));
+    }
+
+    if estimates.is_empty() {
+        None
+    } else {
+        Some(estimates.iter().sum::<i64>() / estimates.len() as i64)
+    }
+}
+
+/// Formats a duration in seconds as e.g. `1h23m`.
+fn format_duration(seconds: i64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    format!("{hours}h{minutes:02}m")
+}
+
+/// Performs `settings.poweraction` via `systemctl`, falling back to
+/// `poweraction_fallback` (if set) when `systemctl` isn't available or fails,
+/// e.g. on non-systemd systems.
+fn perform_power_action(settings: &Settings) -> Result<()> {
+    let systemctl_verb = match settings.poweraction {
+        PowerAction::None => return Ok(()),
+        PowerAction::Suspend => "suspend",
+        PowerAction::Hibernate => "hibernate",
+        PowerAction::Shutdown => "poweroff",
+    };
+
+    let systemctl_ok = Command::new("systemctl")
+        .arg(systemctl_verb)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    if !systemctl_ok {
+        if let Some(fallback) = settings.poweraction_fallback.as_ref() {
+            Command::new("sh")
+                .arg("-c")
+                .arg(fallback)
+                .status()
+                .with_context(|| format!("Failed to run power action fallback {fallback}"))?;
+        }
     }
 
     Ok(())
 }
 
-fn notify_cmd(settings: &Settings, state: &State, charge_percent: i32) -> Result<()> {
+/// Substitutes `{name}` placeholders in `template` with values from `tokens`,
+/// leaving unknown placeholders untouched. `{{` and `}}` are literal braces.
+fn format_template(template: &str, tokens: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c2 in chars.by_ref() {
+                    if c2 == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c2);
+                }
+
+                match tokens.iter().find(|(token, _)| *token == name) {
+                    Some((_, value)) if closed => result.push_str(value),
+                    _ if closed => {
+                        result.push('{');
+                        result.push_str(&name);
+                        result.push('}');
+                    }
+                    _ => {
+                        result.push('{');
+                        result.push_str(&name);
+                    }
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// The freedesktop icon name for `charge_percent`, used when no `-I`/`-L`
+/// icon was configured.
+fn fallback_icon_name(charge_percent: i32, discharging: bool) -> &'static str {
+    if !discharging {
+        if charge_percent >= 100 {
+            "battery-full-charged"
+        } else {
+            "battery-full-charging"
+        }
+    } else if charge_percent <= 10 {
+        "battery-caution"
+    } else if charge_percent <= 40 {
+        "battery-low"
+    } else if charge_percent <= 90 {
+        "battery-good"
+    } else {
+        "battery-full"
+    }
+}
+
+/// Picks the icon to show, preferring `icon_levels` (bucketed by charge
+/// level, with a dedicated charging icon), then the static `-I` icon, then a
+/// freedesktop fallback name.
+fn select_icon(settings: &Settings, charge_percent: i32, discharging: bool) -> Option<String> {
+    if let Some(icon_levels) = settings.icon_levels.as_ref() {
+        if !discharging {
+            if let Some(charging_icon) = icon_levels.get(11) {
+                return Some(charging_icon.clone());
+            }
+        }
+
+        let bucket = (charge_percent.clamp(0, 100) / 10) as usize;
+        icon_levels.get(bucket).cloned()
+    } else if let Some(icon) = settings.icon.as_ref() {
+        Some(icon.clone())
+    } else {
+        Some(fallback_icon_name(charge_percent, discharging).to_string())
+    }
+}
+
+fn notify_cmd(
+    settings: &Settings,
+    state: &State,
+    charge_percent: i32,
+    time_remaining: Option<&str>,
+) -> Result<()> {
     let mut notification = Notification::new()
         .timeout(settings.notification_timeout)
         .appname(settings.appname.as_str())
         .finalize();
 
-    if settings.icon.is_some() {
-        notification = notification
-            .icon(settings.icon.clone().unwrap().as_str())
-            .finalize();
+    let discharging = !matches!(state, State::Charging | State::Full);
+    if let Some(icon) = select_icon(settings, charge_percent, discharging) {
+        notification = notification.icon(icon.as_str()).finalize();
     }
 
-    let summary: &str;
+    let summary_template: &str;
     let mut urgency = Urgency::Normal;
-    let body = format!("Battery level: {}%", charge_percent);
     match state {
-        State::Warning => summary = settings.warningmsg.as_str(),
+        State::Warning => summary_template = settings.warningmsg.as_str(),
         State::Critical => {
-            summary = settings.criticalmsg.as_str();
+            summary_template = settings.criticalmsg.as_str();
             urgency = Urgency::Critical;
         }
-        State::Full => summary = settings.fullmsg.as_str(),
+        State::Full => summary_template = settings.fullmsg.as_str(),
         State::Danger => {
-            if settings.dangercmd.is_some() {
+            if let Some(dangercmd) = settings.dangercmd.as_ref() {
                 Command::new("sh")
                     .arg("-c")
-                    .arg(settings.dangercmd.as_ref().unwrap())
-                    .spawn()
-                    .with_context(|| {
-                        format!("Failed to run {}", settings.dangercmd.clone().unwrap())
-                    })?;
+                    .arg(dangercmd)
+                    .status()
+                    .with_context(|| format!("Failed to run {dangercmd}"))?;
             }
 
+            perform_power_action(settings)?;
+
             return Ok(());
         }
         _ => return Ok(()),
     }
 
+    let percent_str = charge_percent.to_string();
+    let state_name = match state {
+        State::Charging => "charging",
+        State::Discharging => "discharging",
+        State::Warning => "warning",
+        State::Critical => "critical",
+        State::Danger => "danger",
+        State::Full => "full",
+    };
+    let time_str = time_remaining.unwrap_or("unknown");
+    let battery_names = settings
+        .devices
+        .iter()
+        .map(|d| d.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let plural = if settings.devices.len() > 1 { "s" } else { "" };
+
+    let tokens = [
+        ("percent", percent_str.as_str()),
+        ("state", state_name),
+        ("time", time_str),
+        ("battery", battery_names.as_str()),
+        ("plural", plural),
+    ];
+
+    let summary = format_template(summary_template, &tokens);
+    let body = format_template(&settings.bodymsg, &tokens);
+
     notification
         .body(body.as_str())
-        .summary(summary)
+        .summary(summary.as_str())
         .urgency(urgency)
         .show()
         .with_context(|| "Failed to show notification")?;
@@ -408,41 +947,42 @@ fn notify_cmd(settings: &Settings, state: &State, charge_percent: i32) -> Result
 
 fn main() -> Result<()> {
     let mut settings = parse_args()?.validate()?;
-    if settings.batteries.is_empty() {
-        settings.batteries = find_batteries()?
+    if let Some(ups) = settings.ups.clone() {
+        settings.devices = vec![Box::new(UpsBattery::new(ups))];
+    } else if settings.devices.is_empty() {
+        settings.devices = find_batteries()?
     }
 
     let batteries = settings
-        .batteries
+        .devices
         .iter()
-        .map(|b| b.name.clone())
+        .map(|d| d.name().to_string())
         .reduce(|accum: String, item: String| format!("{}, {}", accum, item))
         .unwrap(); // We can unwrap here because finding no batteries is already handled before
 
     println!("Using batteries {batteries}");
 
-    let mut charge: (f64, f64);
     let mut charge_percent: i32;
     let mut discharging: bool;
     let mut state = State::Discharging;
     let mut new_state: State;
 
     loop {
-        update_batteries(&mut settings.batteries)?;
+        for device in settings.devices.iter_mut() {
+            device.refresh()?;
+        }
 
-        charge = settings
-            .batteries
-            .iter()
-            .map(|b| b.energy_now as f64)
-            .zip(settings.batteries.iter().map(|b| b.energy_full as f64))
-            .reduce(|accum, item| (accum.0 + item.0, accum.1 + item.1))
-            .unwrap();
-        charge_percent = (charge.0 / charge.1 * 100.0) as i32;
-
-        discharging = settings
-            .batteries
+        let percents: Vec<i32> = settings
+            .devices
             .iter()
-            .any(|b| b.status == BatteryStatus::Discharging);
+            .filter_map(|device| device.percent())
+            .collect();
+        if percents.is_empty() {
+            bail!("No batteries reporting a usable charge level");
+        }
+        charge_percent = percents.iter().sum::<i32>() / percents.len() as i32;
+
+        discharging = settings.devices.iter().any(|device| device.discharging());
 
         if !discharging {
             if settings.full.is_some() && charge_percent >= settings.full.unwrap() {
@@ -471,7 +1011,8 @@ fn main() -> Result<()> {
         }
 
         if new_state != state {
-            notify_cmd(&settings, &new_state, charge_percent)?;
+            let time_remaining = estimate_remaining_seconds(&settings.devices).map(format_duration);
+            notify_cmd(&settings, &new_state, charge_percent, time_remaining.as_deref())?;
             state = new_state;
         }
 